@@ -8,7 +8,7 @@
 
 use curve25519_dalek::digest::{typenum, FixedOutput, HashMarker, OutputSizeUser, Reset, Update};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct BlakeHasher {
     hasher: blake3::Hasher,
 }
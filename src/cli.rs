@@ -26,8 +26,14 @@ pub(crate) enum Commands {
     Sign(SignArgs),
     /// Verify if the given file is correcly signed and decodes it
     Verify(VerifyArgs),
+    /// Verify a multi-signature bundle against a set of trusted keys, requiring at least a threshold of them to match
+    VerifyThreshold(VerifyThresholdArgs),
+    /// Append another signer's signature to an existing bundle, turning it into a multi-signature bundle
+    Append(AppendArgs),
     /// Generate a new keypair
     Generate(GenerateArgs),
+    /// Download a signed bundle over HTTP, resuming partial downloads, and verify it in one step
+    Fetch(FetchArgs),
     /// Build autocomplete scripts for all the shells supported and save them into the complete folder
     BuildComplete,
 }
@@ -37,11 +43,23 @@ pub(crate) struct SignArgs {
     /// Set the compression level of the file
     #[arg(short, long, default_value_t = 22)]
     pub(crate) compression_level: i32,
+    /// Produce a detached, base64-encoded signature instead of bundling the file and signature together
+    #[arg(short, long)]
+    pub(crate) detached: bool,
+    /// Prompt for the passphrase protecting the signing key
+    #[arg(short = 'P', long)]
+    pub(crate) passphrase: bool,
+    /// Attach a comment that is signed alongside the file; altering it invalidates the signature
+    #[arg(long)]
+    pub(crate) comment: Option<String>,
+    /// Attach a free-form comment that is stored in the bundle but not covered by the signature
+    #[arg(long)]
+    pub(crate) untrusted_comment: Option<String>,
     /// The path of the key to use, the private for signing, the public for verifying
     pub(crate) key_path: PathBuf,
     /// The path of the file to sign
     pub(crate) file_path: PathBuf,
-    /// Where to save signed file
+    /// Where to save signed file. When --detached is set, this is the path of the signature text file
     pub(crate) output_file_path: Option<PathBuf>,
 }
 
@@ -49,14 +67,58 @@ pub(crate) struct SignArgs {
 pub(crate) struct VerifyArgs {
     /// The path of the key to use, the private for signing, the public for verifying
     pub(crate) key_path: PathBuf,
-    /// The path of the file to sign
+    /// The path of the file to verify. When --detached is set, this is the original, unmodified file
+    pub(crate) file_path: PathBuf,
+    /// Verify a detached, base64-encoded signature rather than a bundled file; expects the path of the signature file
+    #[arg(short, long)]
+    pub(crate) detached: Option<PathBuf>,
+    /// Where to save the decoded file. Ignored when --detached is set, since the original file is left untouched
+    pub(crate) output_file_path: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub(crate) struct VerifyThresholdArgs {
+    /// The minimum number of distinct trusted keys that must have a valid signature over the bundle
+    pub(crate) threshold: usize,
+    /// The path of the bundle file to verify
+    pub(crate) file_path: PathBuf,
+    /// Where to save the decoded file
+    pub(crate) output_file_path: Option<PathBuf>,
+    /// Path of a trusted verifying key; pass this flag once per trusted key
+    #[arg(long = "trusted-key", required = true)]
+    pub(crate) trusted_key_paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub(crate) struct AppendArgs {
+    /// Prompt for the passphrase protecting the new signer's private key
+    #[arg(short = 'P', long)]
+    pub(crate) passphrase: bool,
+    /// The path of the new signer's private key
+    pub(crate) key_path: PathBuf,
+    /// The path of the original, unmodified file the bundle was produced from
     pub(crate) file_path: PathBuf,
-    /// Where to save signed file
+    /// The path of the bundle to append the new signature to
+    pub(crate) bundle_path: PathBuf,
+}
+
+#[derive(Args)]
+pub(crate) struct FetchArgs {
+    /// The URL of the signed bundle to download
+    pub(crate) url: String,
+    /// The path of the verifying key
+    pub(crate) key_path: PathBuf,
+    /// Where to save the downloaded bundle; if it already exists, the download is resumed from where it left off
+    pub(crate) bundle_path: PathBuf,
+    /// Where to save the decoded file
     pub(crate) output_file_path: Option<PathBuf>,
 }
 
 #[derive(Args)]
 pub(crate) struct GenerateArgs {
+    /// Prompt for a passphrase to protect the generated private key
+    #[arg(short = 'P', long)]
+    pub(crate) passphrase: bool,
     /// Where to save the private key
     pub(crate) private_key_path: PathBuf,
     /// Where to save the public key
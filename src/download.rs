@@ -0,0 +1,83 @@
+/*
+ * Copyright (c) 2024 Matteo Franceschini
+ * All rights reserved.
+ *
+ * Use of this source code is governed by BSD-3-Clause-Clear
+ * license that can be found in the LICENSE file
+ */
+
+use log::info;
+use reqwest::{
+    blocking::Client,
+    header::{CONTENT_LENGTH, RANGE},
+    StatusCode,
+};
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::BinsignError;
+
+/// Size, in bytes, of the chunks streamed from the network to disk.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Download the bundle at `url` into `output_path`, resuming a previously interrupted download if `output_path`
+/// already exists.\
+/// `on_progress` is called after every chunk with the number of bytes downloaded so far and, when the server
+/// reports a `Content-Length`, the total size of the bundle.
+pub fn download_bundle<P: AsRef<Path>>(
+    url: &str,
+    output_path: P,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<(), BinsignError> {
+    let output_path = output_path.as_ref();
+    let mut resume_offset = output_path
+        .metadata()
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    info!("Requesting bundle, resuming from byte {resume_offset}...");
+    let client = Client::new();
+    let mut request = client.get(url);
+    if resume_offset > 0 {
+        request = request.header(RANGE, format!("bytes={resume_offset}-"));
+    }
+    let mut response = request.send().map_err(BinsignError::Download)?;
+    if !response.status().is_success() {
+        return Err(BinsignError::DownloadStatus(response.status()));
+    }
+    let mut output_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(output_path)
+        .map_err(BinsignError::FileIO)?;
+    if resume_offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+        info!("Server does not support resuming, restarting the download...");
+        output_file.set_len(0).map_err(BinsignError::FileIO)?;
+        resume_offset = 0;
+    }
+    output_file
+        .seek(SeekFrom::Start(resume_offset))
+        .map_err(BinsignError::FileIO)?;
+    let total_size = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(|content_length| content_length + resume_offset);
+    let mut completed_bytes = resume_offset;
+    let mut buffer = [0u8; CHUNK_SIZE];
+    loop {
+        let read = response.read(&mut buffer).map_err(BinsignError::Download)?;
+        if read == 0 {
+            break;
+        }
+        output_file
+            .write_all(&buffer[..read])
+            .map_err(BinsignError::FileIO)?;
+        completed_bytes += read as u64;
+        on_progress(completed_bytes, total_size);
+    }
+    Ok(())
+}
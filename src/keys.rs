@@ -6,22 +6,64 @@
  * license that can be found in the LICENSE file
  */
 
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    XChaCha20Poly1305, XNonce,
+};
 use ed25519_dalek::{
     pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey},
     SigningKey, VerifyingKey,
 };
-use rand::SeedableRng;
+use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
 use std::{fs, path::Path};
 
-use crate::BinsignError;
+use crate::{
+    signed_file::{KeyId, KEY_ID_LENGTH},
+    BinsignError,
+};
+
+/// Magic tag identifying an encrypted private key container; written as the first bytes of the file so
+/// [`read_keypair_from_file`] can tell an encrypted key apart from a plain PKCS#8 DER one.
+const MAGIC: [u8; 4] = *b"BSK1";
+/// Size, in bytes, of the random Argon2id salt.
+const SALT_LENGTH: usize = 16;
+/// Size, in bytes, of the random XChaCha20-Poly1305 nonce.
+const NONCE_LENGTH: usize = 24;
+/// Size, in bytes, of the symmetric key derived by Argon2id.
+const DERIVED_KEY_LENGTH: usize = 32;
+/// Tuned Argon2id parameters: 19 MiB of memory, 2 iterations, 1 degree of parallelism.
+const ARGON2_MEMORY_COST_KIB: u32 = 19456;
+const ARGON2_TIME_COST: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+/// On-disk container for a passphrase-encrypted private key.
+#[derive(Serialize, Deserialize)]
+struct EncryptedKey {
+    magic: [u8; 4],
+    salt: [u8; SALT_LENGTH],
+    nonce: [u8; NONCE_LENGTH],
+    #[serde(with = "serde_bytes")]
+    ciphertext: Vec<u8>,
+}
 
 /// Expect the path of the private key.\
+/// If the key is passphrase-protected, `passphrase` must be provided to decrypt it, otherwise
+/// [`BinsignError::PassphraseRequired`] is returned.\
 /// Return the decoded private key and the derived public key.
 pub fn read_keypair_from_file<P: AsRef<Path>>(
     path: P,
+    passphrase: Option<&str>,
 ) -> Result<(SigningKey, VerifyingKey), BinsignError> {
-    let private_der = fs::read(path).map_err(BinsignError::FileIO)?;
+    let stored = fs::read(path).map_err(BinsignError::FileIO)?;
+    let private_der = if stored.starts_with(&MAGIC) {
+        let passphrase = passphrase.ok_or(BinsignError::PassphraseRequired)?;
+        decrypt_private_key(&stored, passphrase)?
+    } else {
+        stored
+    };
     let signing_key: SigningKey = SigningKey::from_pkcs8_der(&private_der)
         .map_err(BinsignError::PrivateKeyDeserialization)?;
     let verifying_key: VerifyingKey = signing_key.verifying_key();
@@ -37,10 +79,13 @@ pub fn read_verifying_key_from_file<P: AsRef<Path>>(path: P) -> Result<Verifying
 }
 
 /// Generate a new keypair and save it at the provided path.\
-/// Expect the signing key path first and then the verifying key path.
+/// Expect the signing key path first and then the verifying key path.\
+/// When `passphrase` is provided, the private key is encrypted at rest with it; otherwise it is stored as plain
+/// PKCS#8 DER, like before passphrase protection was added.
 pub fn generate_keypair<P: AsRef<Path>>(
     signing_key_path: P,
     verifying_key_path: P,
+    passphrase: Option<&str>,
 ) -> Result<(), BinsignError> {
     let mut csprng = ChaCha20Rng::from_entropy();
     let signing_key: SigningKey = SigningKey::generate(&mut csprng);
@@ -51,7 +96,99 @@ pub fn generate_keypair<P: AsRef<Path>>(
     let public_der = verifying_key
         .to_public_key_der()
         .map_err(BinsignError::PublicKeySerialization)?;
-    fs::write(signing_key_path, private_der.as_bytes()).map_err(BinsignError::FileIO)?;
+    let private_bytes = match passphrase {
+        Some(passphrase) => encrypt_private_key(private_der.as_bytes(), passphrase, &mut csprng)?,
+        None => private_der.as_bytes().to_vec(),
+    };
+    fs::write(signing_key_path, private_bytes).map_err(BinsignError::FileIO)?;
     fs::write(verifying_key_path, public_der.as_bytes()).map_err(BinsignError::FileIO)?;
     Ok(())
 }
+
+/// Compute the short fingerprint of a verifying key: the first 8 bytes of the blake3 hash of its DER encoding.\
+/// Used to tell which key produced a given signature without shipping the whole key alongside it.
+pub fn key_id(verifying_key: &VerifyingKey) -> Result<KeyId, BinsignError> {
+    let public_der = verifying_key
+        .to_public_key_der()
+        .map_err(BinsignError::PublicKeySerialization)?;
+    let hash = blake3::hash(public_der.as_bytes());
+    let mut id = [0u8; KEY_ID_LENGTH];
+    id.copy_from_slice(&hash.as_bytes()[..KEY_ID_LENGTH]);
+    Ok(id)
+}
+
+/// Derive a 32-byte symmetric key from a passphrase and salt using Argon2id with tuned, memory-hard parameters.
+fn derive_key(passphrase: &str, salt: &[u8; SALT_LENGTH]) -> Result<[u8; DERIVED_KEY_LENGTH], BinsignError> {
+    let params = Params::new(
+        ARGON2_MEMORY_COST_KIB,
+        ARGON2_TIME_COST,
+        ARGON2_PARALLELISM,
+        Some(DERIVED_KEY_LENGTH),
+    )
+    .map_err(BinsignError::KeyDerivation)?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let mut key = [0u8; DERIVED_KEY_LENGTH];
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(BinsignError::KeyDerivation)?;
+    Ok(key)
+}
+
+/// Encrypt a PKCS#8 DER private key with a passphrase, returning the bincode-encoded [`EncryptedKey`] container.
+fn encrypt_private_key(
+    der: &[u8],
+    passphrase: &str,
+    csprng: &mut ChaCha20Rng,
+) -> Result<Vec<u8>, BinsignError> {
+    let mut salt = [0u8; SALT_LENGTH];
+    csprng.fill_bytes(&mut salt);
+    let mut nonce_bytes = [0u8; NONCE_LENGTH];
+    csprng.fill_bytes(&mut nonce_bytes);
+    let key = derive_key(passphrase, &salt)?;
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce_bytes), der)
+        .map_err(|_| BinsignError::KeyEncryption)?;
+    let container = EncryptedKey {
+        magic: MAGIC,
+        salt,
+        nonce: nonce_bytes,
+        ciphertext,
+    };
+    bincode::serialize(&container).map_err(BinsignError::FileEncoding)
+}
+
+/// Decrypt an [`EncryptedKey`] container with a passphrase, returning the PKCS#8 DER private key.\
+/// A failure here means either the passphrase is wrong or the container is corrupted: AEAD authentication does
+/// not distinguish between the two.
+fn decrypt_private_key(stored: &[u8], passphrase: &str) -> Result<Vec<u8>, BinsignError> {
+    let container: EncryptedKey = bincode::deserialize(stored).map_err(BinsignError::FileDecoding)?;
+    let key = derive_key(passphrase, &container.salt)?;
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    cipher
+        .decrypt(XNonce::from_slice(&container.nonce), container.ciphertext.as_ref())
+        .map_err(|_| BinsignError::WrongPassphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_then_decrypt_private_key_round_trip() {
+        let mut csprng = ChaCha20Rng::from_entropy();
+        let der = b"a fake PKCS#8 DER private key".to_vec();
+        let encrypted = encrypt_private_key(&der, "correct horse battery staple", &mut csprng).unwrap();
+        let decrypted = decrypt_private_key(&encrypted, "correct horse battery staple").unwrap();
+        assert_eq!(decrypted, der);
+    }
+
+    #[test]
+    fn decrypt_private_key_with_wrong_passphrase_fails() {
+        let mut csprng = ChaCha20Rng::from_entropy();
+        let der = b"a fake PKCS#8 DER private key".to_vec();
+        let encrypted = encrypt_private_key(&der, "correct horse battery staple", &mut csprng).unwrap();
+        let result = decrypt_private_key(&encrypted, "wrong passphrase");
+        assert!(matches!(result, Err(BinsignError::WrongPassphrase)));
+    }
+}
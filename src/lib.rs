@@ -8,30 +8,43 @@
 
 //! # Binsign
 //! A tool to sign and encode file, inspired by [minisign](https://github.com/jedisct1/minisign).\
-//! Unlike minisign, which output a file with only the signature, leaving the original file untouched, binsign will bundle together the signature and file in a new file.
+//! By default binsign bundles together the signature and file in a new file, but it can also produce a detached
+//! signature like minisign does, leaving the original file untouched.
 //! ## Dependencies
 //! The [bincode] crate and the [serde_bytes] crate are used for serialization of the files.\
 //! For signing and verifying, the [ed25519_dalek] crate is used, in combination with the [blake3] crate: the file is firstly hashed by blake3, and then the hash is signed.\
 //! The [rand_chacha] crate is used as cryptographically secure random number generator for key generation.\
-//! The [zstd] crate is used for data compression.
+//! The [zstd] crate is used for data compression.\
+//! The [base64] crate is used to encode detached signatures as portable text.\
+//! The [reqwest] crate is used to fetch and verify signed bundles over HTTP.\
+//! The [argon2] crate derives a symmetric key from a passphrase, and the [chacha20poly1305] crate uses it to
+//! encrypt private keys at rest.
+//!
+//! Like minisign, a bundle can carry a trusted comment: arbitrary metadata that is signed alongside the file and
+//! an untrusted comment that is not.
 //!
 //! ## Notes
 //! This implementation is not guaranteed to be cryptographically safe. I am not an expert in cryptography.\
 //! The main concern is the use of blake3 hasher instead of SHA512, the one used by ed25519_dalek.
 
-use ed25519_dalek::Digest;
-use keys::{read_keypair_from_file, read_verifying_key_from_file};
+use download::download_bundle;
+use ed25519_dalek::{Digest, VerifyingKey};
+use keys::{key_id, read_keypair_from_file, read_verifying_key_from_file};
 use log::info;
-use signed_file::SignedFile;
+use signed_file::{DecodedBody, DetachedSignature, SignedFile};
 use std::{
     fs,
-    mem::size_of_val,
+    fs::File,
+    io,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
 use crate::blake::BlakeHasher;
 
+/// Fetch-and-verify over HTTP
+pub mod download;
 /// Key manipulation utils
 pub mod keys;
 /// Signed file model
@@ -39,15 +52,28 @@ pub mod signed_file;
 
 mod blake;
 
+/// Size, in bytes, of the chunks streamed between the input file, the hasher and the compressor.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
 /// Sign the provided file with the provided private key.\
 /// Expect the path where the file to sign and the key are located, the path of the output bundle and the compression level.\
 /// If `None` is passed instead of the output path, the bundle will be saved in the same place where the file to sign is located, using the .sig extension.\
-/// The bundle contains the file signature and the file itself.
+/// The bundle contains the file signature and the file itself.\
+/// The file is streamed in fixed-size chunks rather than being loaded whole into memory, so signing does not require
+/// RAM proportional to the file size.\
+/// `passphrase` must be provided if the signing key is passphrase-protected.\
+/// `trusted_comment`, when provided, is authenticated alongside the file itself: it is concatenated with the
+/// file's blake3 prehash before signing, so altering it invalidates every signature over the bundle. Like
+/// minisign's trusted comments, it is useful for metadata such as a timestamp, a version string or the original
+/// file name. `untrusted_comment` is stored next to it but is not covered by the signature at all.
 pub fn sign_file<P: AsRef<Path>>(
     file_path: P,
     signing_key_path: P,
     output_path: Option<P>,
     compression_level: i32,
+    passphrase: Option<&str>,
+    trusted_comment: Option<String>,
+    untrusted_comment: Option<String>,
 ) -> Result<(), BinsignError> {
     let output_path = match output_path {
         Some(path) => PathBuf::from(path.as_ref()),
@@ -59,32 +85,71 @@ pub fn sign_file<P: AsRef<Path>>(
         }
     };
     info!("Reading signing key...");
-    let (signing_key, _) = read_keypair_from_file(signing_key_path)?;
-    info!("Reading file...");
-    let file_content = fs::read(file_path).map_err(BinsignError::FileIO)?;
-    let original_file_size = size_of_val(&*file_content);
+    let (signing_key, verifying_key) = read_keypair_from_file(signing_key_path, passphrase)?;
+    info!("Opening input file...");
+    let input_file = File::open(file_path).map_err(BinsignError::FileIO)?;
+    let original_file_size = input_file.metadata().map_err(BinsignError::FileIO)?.len();
     info!("Original file size (in bytes): {original_file_size}");
-    info!("Hashing file...");
-    let file_hash = get_file_hasher(&file_content);
+    let mut reader = BufReader::new(input_file);
+    let mut output_file = File::create(&output_path).map_err(BinsignError::FileIO)?;
+    let header_size =
+        SignedFile::placeholder_size(1, trusted_comment.clone(), untrusted_comment.clone())?;
+    info!("Reserving header space...");
+    output_file
+        .write_all(&vec![0u8; header_size as usize])
+        .map_err(BinsignError::FileIO)?;
+    info!("Compressing and hashing file...");
+    let mut hasher = BlakeHasher::new();
+    let mut encoder = zstd::stream::Encoder::new(BufWriter::new(&output_file), compression_level)
+        .map_err(BinsignError::ZstdCompression)?;
+    let mut buffer = [0u8; CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer).map_err(BinsignError::FileIO)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        encoder
+            .write_all(&buffer[..read])
+            .map_err(BinsignError::ZstdCompression)?;
+    }
+    encoder
+        .finish()
+        .map_err(BinsignError::ZstdCompression)?
+        .flush()
+        .map_err(BinsignError::FileIO)?;
     info!("Signing hash...");
     let signature = signing_key
-        .sign_prehashed(file_hash, None)
+        .sign_prehashed(commit_hasher(hasher, trusted_comment.as_deref()), None)
         .map_err(BinsignError::Signing)?;
-    info!("Compressing...");
-    let file_content = zstd::bulk::compress(&file_content, compression_level)
-        .map_err(BinsignError::ZstdCompression)?;
-    let signed_file = SignedFile::new(file_content, signature, original_file_size as u64);
-    info!("Encoding file...");
-    let encoded_file = signed_file.encode()?;
-    info!("Writing file...");
-    fs::write(output_path, encoded_file).map_err(BinsignError::FileIO)?;
+    info!("Writing header...");
+    let signed_file = SignedFile::new(
+        key_id(&verifying_key)?,
+        signature,
+        original_file_size,
+        trusted_comment,
+        untrusted_comment,
+    );
+    let encoded_header = signed_file.encode()?;
+    output_file
+        .seek(SeekFrom::Start(0))
+        .map_err(BinsignError::FileIO)?;
+    output_file
+        .write_all(&encoded_header)
+        .map_err(BinsignError::FileIO)?;
     Ok(())
 }
 
 /// Verify if the provided bundle file is correctly signed using the provided public key.\
 /// Expect the path where the file to verify and the key are located and the path of the output decoded file.\
 /// If `None` is passed instead of the output path, the decoded file will be saved in the same place where the file to veify is located, using the .ver extension.\
-/// The decoded file is just the bundle file without the signature.
+/// The decoded file is just the bundle file without the signature.\
+/// The bundle is streamed in fixed-size chunks rather than being loaded whole into memory, so verifying does not
+/// require RAM proportional to the file size.\
+/// Succeeds if any of the bundle's signatures (there may be more than one, see [`append_signature`]) verifies
+/// against the provided key; use [`verify_file_threshold`] to require several distinct trusted keys at once.\
+/// If the bundle carries a trusted comment, it is authenticated alongside the file (altering it invalidates every
+/// signature) and printed via [`log::info`] once verification succeeds.
 pub fn verify_file<P: AsRef<Path>>(
     file_path: P,
     verifying_key_path: P,
@@ -101,33 +166,300 @@ pub fn verify_file<P: AsRef<Path>>(
     };
     info!("Reading verifying key...");
     let verifying_key = read_verifying_key_from_file(verifying_key_path)?;
-    info!("Reading file...");
-    let file_content = fs::read(file_path).map_err(BinsignError::FileIO)?;
-    info!("Decoding file...");
-    let signed_file = SignedFile::decode(file_content)?;
-    let signature = signed_file.signature;
-    let file_content = signed_file.file;
-    let original_file_size = signed_file.file_size;
-    info!("Decompressing...");
-    let file_content = zstd::bulk::decompress(&file_content, original_file_size as usize)
-        .map_err(BinsignError::ZstdDecompression)?;
+    let (signed_file, hasher, temp_output_path) = decode_and_hash_body(file_path, &output_path)?;
+    info!("Verifying...");
+    let hasher = commit_hasher(hasher, signed_file.trusted_comment.as_deref());
+    let verified = signed_file
+        .signatures
+        .iter()
+        .any(|(_, signature)| verifying_key.verify_prehashed(hasher.clone(), None, signature).is_ok());
+    if !verified {
+        let _ = fs::remove_file(&temp_output_path);
+        return Err(BinsignError::Verification(ed25519_dalek::SignatureError::new()));
+    }
+    fs::rename(&temp_output_path, &output_path).map_err(BinsignError::FileIO)?;
+    if let Some(trusted_comment) = &signed_file.trusted_comment {
+        info!("Verified trusted comment: {trusted_comment}");
+    }
+    Ok(())
+}
+
+/// Verify a multi-signature bundle against a set of trusted verifying keys, succeeding only when at least
+/// `threshold` distinct trusted keys have a valid signature over the bundle's prehash.\
+/// Mirrors how release tooling collects and checks multiple independent signatures over one artifact.
+pub fn verify_file_threshold<P: AsRef<Path>>(
+    file_path: P,
+    output_path: Option<P>,
+    trusted_verifying_keys: &[VerifyingKey],
+    threshold: usize,
+) -> Result<(), BinsignError> {
+    let output_path = match output_path {
+        Some(path) => PathBuf::from(path.as_ref()),
+        None => {
+            let file_path = file_path.as_ref();
+            let file_path = file_path.display();
+            let path = format!("{file_path}.ver");
+            PathBuf::from(&path)
+        }
+    };
+    let (signed_file, hasher, temp_output_path) = decode_and_hash_body(file_path, &output_path)?;
+    info!("Verifying threshold...");
+    let hasher = commit_hasher(hasher, signed_file.trusted_comment.as_deref());
+    let satisfied = trusted_verifying_keys
+        .iter()
+        .enumerate()
+        // A key listed more than once still counts only once towards the threshold.
+        .filter(|(index, verifying_key)| !trusted_verifying_keys[..*index].contains(verifying_key))
+        .filter(|(_, verifying_key)| {
+            signed_file
+                .signatures
+                .iter()
+                .any(|(_, signature)| verifying_key.verify_prehashed(hasher.clone(), None, signature).is_ok())
+        })
+        .count();
+    if satisfied < threshold {
+        let _ = fs::remove_file(&temp_output_path);
+        return Err(BinsignError::ThresholdNotMet {
+            required: threshold,
+            satisfied,
+        });
+    }
+    fs::rename(&temp_output_path, &output_path).map_err(BinsignError::FileIO)?;
+    if let Some(trusted_comment) = &signed_file.trusted_comment {
+        info!("Verified trusted comment: {trusted_comment}");
+    }
+    Ok(())
+}
+
+/// Add another signer's signature to an existing bundle, producing (or extending) a multi-signature bundle.\
+/// Expect the path of the original, unmodified file the bundle was produced from, the path of the bundle to
+/// append to and the path of the new signer's private key.\
+/// The new signer must supply the same original file used to produce the bundle, since the signature is produced
+/// over its blake3 prehash, exactly like the first signature. The existing compressed body is left untouched; only
+/// the header is rewritten. Appending a signature to a legacy, pre-streaming bundle (see [`SignedFile::decode`])
+/// also upgrades it to the current, streamed layout as a side effect.\
+/// `passphrase` must be provided if the new signer's key is passphrase-protected.
+pub fn append_signature<P: AsRef<Path>>(
+    file_path: P,
+    bundle_path: P,
+    signing_key_path: P,
+    passphrase: Option<&str>,
+) -> Result<(), BinsignError> {
+    let bundle_path = bundle_path.as_ref();
+    info!("Reading signing key...");
+    let (signing_key, verifying_key) = read_keypair_from_file(signing_key_path, passphrase)?;
     info!("Hashing file...");
-    let file_hasher = get_file_hasher(&file_content);
+    let hasher = hash_file(file_path)?;
+    info!("Reading existing bundle header...");
+    let mut bundle_file = File::open(bundle_path).map_err(BinsignError::FileIO)?;
+    let (mut signed_file, body) = SignedFile::decode(&mut bundle_file)?;
+    info!("Signing hash...");
+    let hasher = commit_hasher(hasher, signed_file.trusted_comment.as_deref());
+    let signature = signing_key
+        .sign_prehashed(hasher, None)
+        .map_err(BinsignError::Signing)?;
+    signed_file.append_signature(key_id(&verifying_key)?, signature);
+    info!("Rewriting bundle with new signature...");
+    let temp_path = PathBuf::from(format!("{}.tmp", bundle_path.display()));
+    let mut temp_file = File::create(&temp_path).map_err(BinsignError::FileIO)?;
+    let encoded_header = signed_file.encode()?;
+    temp_file
+        .write_all(&encoded_header)
+        .map_err(BinsignError::FileIO)?;
+    match body {
+        // The compressed body follows the header on the reader: copy it over as-is.
+        DecodedBody::Streamed => {
+            io::copy(&mut bundle_file, &mut temp_file).map_err(BinsignError::FileIO)?;
+        }
+        // A legacy bundle's compressed body was recovered from its embedded header; writing it out after the new
+        // header upgrades the bundle to the current, streamed layout.
+        DecodedBody::Embedded(compressed_body) => {
+            temp_file
+                .write_all(&compressed_body)
+                .map_err(BinsignError::FileIO)?;
+        }
+    }
+    fs::rename(&temp_path, bundle_path).map_err(BinsignError::FileIO)?;
+    Ok(())
+}
+
+/// Decode a bundle's header, then stream-decompress its body into a temporary file next to `output_path`, hashing
+/// the plaintext chunks as they are written. Returns the decoded header, the finalized hasher and the path of the
+/// temporary file, ready to be verified.\
+/// The decoded content is never written straight to `output_path`: it may be attacker-controlled, so callers must
+/// only rename the temporary file into place once the bundle's signature has actually been verified, and delete it
+/// otherwise.
+fn decode_and_hash_body<P: AsRef<Path>>(
+    file_path: P,
+    output_path: P,
+) -> Result<(SignedFile, BlakeHasher, PathBuf), BinsignError> {
+    info!("Opening bundle file...");
+    let mut input_file = File::open(file_path).map_err(BinsignError::FileIO)?;
+    let (signed_file, body) = SignedFile::decode(&mut input_file)?;
+    info!("Decompressing and hashing file...");
+    let temp_output_path = PathBuf::from(format!("{}.tmp", output_path.as_ref().display()));
+    let output_file = File::create(&temp_output_path).map_err(BinsignError::FileIO)?;
+    let mut writer = BufWriter::new(output_file);
+    let hasher = match body {
+        // The current bundle layout: the compressed body is streamed right off the reader, straight after the
+        // header.
+        DecodedBody::Streamed => zstd::stream::Decoder::new(BufReader::new(input_file))
+            .map_err(BinsignError::ZstdDecompression)
+            .and_then(|decoder| decompress_hash_and_write(decoder, &mut writer)),
+        // A legacy bundle's whole compressed body was already recovered into memory while decoding its embedded
+        // header; decompress it from there instead.
+        DecodedBody::Embedded(compressed_body) => zstd::stream::Decoder::new(io::Cursor::new(compressed_body))
+            .map_err(BinsignError::ZstdDecompression)
+            .and_then(|decoder| decompress_hash_and_write(decoder, &mut writer)),
+    }
+    .and_then(|hasher| writer.flush().map(|_| hasher).map_err(BinsignError::FileIO));
+    if hasher.is_err() {
+        // Don't leave partially decoded, unverified content behind.
+        let _ = fs::remove_file(&temp_output_path);
+    }
+    Ok((signed_file, hasher?, temp_output_path))
+}
+
+/// Stream-decompress `decoder` in fixed-size chunks, hashing each plaintext chunk with blake3 as it is written to
+/// `writer`. Shared by both bundle body layouts [`decode_and_hash_body`] can encounter.
+fn decompress_hash_and_write<R: Read, W: Write>(
+    mut decoder: R,
+    writer: &mut W,
+) -> Result<BlakeHasher, BinsignError> {
+    let mut hasher = BlakeHasher::new();
+    let mut buffer = [0u8; CHUNK_SIZE];
+    loop {
+        let read = decoder
+            .read(&mut buffer)
+            .map_err(BinsignError::ZstdDecompression)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer
+            .write_all(&buffer[..read])
+            .map_err(BinsignError::FileIO)?;
+    }
+    Ok(hasher)
+}
+
+/// Sign the provided file with the provided private key, producing a detached signature.\
+/// Unlike [`sign_file`], the original file is left untouched: only the signature, encoded as portable base64 text,
+/// is written to the output path.\
+/// If `None` is passed instead of the output path, the signature will be saved in the same place where the file to
+/// sign is located, using the .sig extension.\
+/// `passphrase` must be provided if the signing key is passphrase-protected.
+pub fn sign_file_detached<P: AsRef<Path>>(
+    file_path: P,
+    signing_key_path: P,
+    output_path: Option<P>,
+    passphrase: Option<&str>,
+) -> Result<(), BinsignError> {
+    let output_path = match output_path {
+        Some(path) => PathBuf::from(path.as_ref()),
+        None => {
+            let file_path = file_path.as_ref();
+            let file_path = file_path.display();
+            let path = format!("{file_path}.sig");
+            PathBuf::from(&path)
+        }
+    };
+    info!("Reading signing key...");
+    let (signing_key, verifying_key) = read_keypair_from_file(signing_key_path, passphrase)?;
+    info!("Hashing file...");
+    let hasher = hash_file(file_path)?;
+    info!("Signing hash...");
+    let signature = signing_key
+        .sign_prehashed(hasher, None)
+        .map_err(BinsignError::Signing)?;
+    let detached_signature = DetachedSignature::new(key_id(&verifying_key)?, signature);
+    info!("Writing signature...");
+    fs::write(output_path, detached_signature.to_base64()).map_err(BinsignError::FileIO)?;
+    Ok(())
+}
+
+/// Verify a detached signature produced by [`sign_file_detached`] against the unmodified original file.\
+/// Expect the path of the original file, the verifying key and the base64-encoded signature file.
+pub fn verify_file_detached<P: AsRef<Path>>(
+    file_path: P,
+    verifying_key_path: P,
+    signature_path: P,
+) -> Result<(), BinsignError> {
+    info!("Reading verifying key...");
+    let verifying_key = read_verifying_key_from_file(verifying_key_path)?;
+    info!("Reading signature...");
+    let encoded_signature = fs::read_to_string(signature_path).map_err(BinsignError::FileIO)?;
+    let detached_signature = DetachedSignature::from_base64(&encoded_signature)?;
+    info!("Hashing file...");
+    let hasher = hash_file(file_path)?;
     info!("Verifying...");
     verifying_key
-        .verify_prehashed(file_hasher, None, &signature)
+        .verify_prehashed(hasher, None, &detached_signature.signature)
         .map_err(BinsignError::Verification)?;
-    info!("Writing decoded file...");
-    fs::write(output_path, file_content).map_err(BinsignError::FileIO)?;
     Ok(())
 }
 
-fn get_file_hasher(file_data: &[u8]) -> BlakeHasher {
+/// Download a signed bundle from `url` and verify it in one step.\
+/// Combines [`download::download_bundle`] and [`verify_file`]: the bundle is streamed to `bundle_path` with resume
+/// support, `on_progress` is called as bytes arrive, and the downloaded bundle is deleted if verification fails.\
+/// `verify_file` itself never leaves a decoded output on disk unless its signature actually verifies, so together
+/// neither the bundle nor its decoded content outlive a failed verification.
+pub fn fetch_and_verify_file<P: AsRef<Path>>(
+    url: &str,
+    verifying_key_path: P,
+    bundle_path: P,
+    output_path: Option<P>,
+    on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<(), BinsignError> {
+    info!("Downloading bundle...");
+    download_bundle(url, bundle_path.as_ref(), on_progress)?;
+    info!("Verifying downloaded bundle...");
+    let result = verify_file(
+        bundle_path.as_ref(),
+        verifying_key_path.as_ref(),
+        output_path.as_ref().map(|path| path.as_ref()),
+    );
+    if result.is_err() {
+        info!("Verification failed, deleting downloaded bundle...");
+        let _ = fs::remove_file(bundle_path.as_ref());
+    }
+    result
+}
+
+/// Fold a finalized content hasher and the bundle's trusted comment, if any, into a hasher ready to be signed or
+/// verified.\
+/// Without a trusted comment the content hasher is returned as-is, so the signature covers the file's prehash
+/// alone, just like a legacy bundle's signature. With one, the comment's UTF-8 bytes are concatenated after the
+/// content's prehash, so a signature produced this way authenticates both the file and the comment at once:
+/// altering either invalidates it.
+fn commit_hasher(content_hasher: BlakeHasher, trusted_comment: Option<&str>) -> BlakeHasher {
+    let Some(trusted_comment) = trusted_comment else {
+        return content_hasher;
+    };
+    let content_hash = content_hasher.finalize();
     let mut hasher = BlakeHasher::new();
-    hasher.update(file_data);
+    hasher.update(&content_hash);
+    hasher.update(trusted_comment.as_bytes());
     hasher
 }
 
+/// Stream the file at the provided path through the blake3 hasher, in fixed-size chunks, without buffering it
+/// whole in memory.
+fn hash_file<P: AsRef<Path>>(file_path: P) -> Result<BlakeHasher, BinsignError> {
+    let file = File::open(file_path).map_err(BinsignError::FileIO)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = BlakeHasher::new();
+    let mut buffer = [0u8; CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer).map_err(BinsignError::FileIO)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher)
+}
+
 /// Errors
 #[derive(Debug, Error)]
 pub enum BinsignError {
@@ -157,4 +489,99 @@ pub enum BinsignError {
     ZstdCompression(std::io::Error),
     #[error("An error occurred during file decompression. Details: {0}")]
     ZstdDecompression(std::io::Error),
+    #[error("An error occurred while decoding a base64-encoded signature. Details: {0}")]
+    SignatureBase64Decoding(base64::DecodeError),
+    #[error("The detached signature is malformed")]
+    MalformedDetachedSignature,
+    #[error("Only {satisfied} of the required {required} trusted keys have a valid signature over this bundle")]
+    ThresholdNotMet { required: usize, satisfied: usize },
+    #[error("An error occurred while downloading the bundle. Details: {0}")]
+    Download(reqwest::Error),
+    #[error("The server responded with an unexpected status while downloading the bundle: {0}")]
+    DownloadStatus(reqwest::StatusCode),
+    #[error("An error occurred while deriving a key from the passphrase. Details: {0}")]
+    KeyDerivation(argon2::Error),
+    #[error("An error occurred while encrypting the private key")]
+    KeyEncryption,
+    #[error("This private key is passphrase-protected; a passphrase is required to read it")]
+    PassphraseRequired,
+    #[error("Wrong passphrase, or the private key file is corrupted")]
+    WrongPassphrase,
+    #[error("An error occurred while prompting for the passphrase. Details: {0}")]
+    PassphrasePrompt(std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keys::generate_keypair;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Build a path under the system temp directory unique to this test run, so parallel tests don't collide.
+    fn temp_path(name: &str) -> PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("binsign-test-{}-{}-{name}", std::process::id(), unique))
+    }
+
+    #[test]
+    fn sign_then_verify_round_trip() {
+        let signing_key_path = temp_path("signing.key");
+        let verifying_key_path = temp_path("verifying.key");
+        generate_keypair(&signing_key_path, &verifying_key_path, None).unwrap();
+        let file_path = temp_path("file.txt");
+        fs::write(&file_path, b"a message worth signing").unwrap();
+        let bundle_path = temp_path("file.sig");
+        sign_file(
+            &file_path,
+            &signing_key_path,
+            Some(bundle_path.clone()),
+            3,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let output_path = temp_path("file.ver");
+        verify_file(&bundle_path, &verifying_key_path, Some(output_path.clone())).unwrap();
+        assert_eq!(fs::read(&output_path).unwrap(), b"a message worth signing");
+        for path in [signing_key_path, verifying_key_path, file_path, bundle_path, output_path] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn tampering_with_trusted_comment_invalidates_signature() {
+        let signing_key_path = temp_path("signing.key");
+        let verifying_key_path = temp_path("verifying.key");
+        generate_keypair(&signing_key_path, &verifying_key_path, None).unwrap();
+        let file_path = temp_path("file.txt");
+        fs::write(&file_path, b"a message worth signing").unwrap();
+        let bundle_path = temp_path("file.sig");
+        sign_file(
+            &file_path,
+            &signing_key_path,
+            Some(bundle_path.clone()),
+            3,
+            None,
+            Some("timestamp: 1".to_string()),
+            None,
+        )
+        .unwrap();
+        let mut bundle_file = File::open(&bundle_path).unwrap();
+        let (mut signed_file, _) = SignedFile::decode(&mut bundle_file).unwrap();
+        drop(bundle_file);
+        signed_file.trusted_comment = Some("timestamp: 2".to_string());
+        let mut tampered = signed_file.encode().unwrap();
+        let original = fs::read(&bundle_path).unwrap();
+        tampered.extend_from_slice(&original[tampered.len()..]);
+        fs::write(&bundle_path, tampered).unwrap();
+        let output_path = temp_path("file.ver");
+        let result = verify_file(&bundle_path, &verifying_key_path, Some(output_path.clone()));
+        assert!(matches!(result, Err(BinsignError::Verification(_))));
+        assert!(!output_path.exists());
+        for path in [signing_key_path, verifying_key_path, file_path, bundle_path] {
+            let _ = fs::remove_file(path);
+        }
+    }
 }
@@ -6,11 +6,17 @@
  * license that can be found in the LICENSE file
  */
 
-use binsign::{keys::generate_keypair, sign_file, verify_file, BinsignError};
+use binsign::{
+    append_signature, fetch_and_verify_file,
+    keys::{generate_keypair, read_verifying_key_from_file},
+    sign_file, sign_file_detached, verify_file, verify_file_detached, verify_file_threshold,
+    BinsignError,
+};
 use clap::{CommandFactory, Parser, ValueEnum};
 use clap_complete::Shell;
 use cli::Cli;
-use log::{error, LevelFilter};
+use log::{error, info, LevelFilter};
+use rpassword::prompt_password;
 use std::{
     fs::{create_dir, File},
     io::Write,
@@ -47,28 +53,93 @@ fn main() {
 fn handle_commands(command: cli::Commands) -> Result<(), BinsignError> {
     match command {
         cli::Commands::Sign(sign_args) => {
-            sign_file(
-                sign_args.file_path,
-                sign_args.key_path,
-                sign_args.output_file_path,
-                sign_args.compression_level,
-            )?;
+            let passphrase = prompt_passphrase_if_needed(sign_args.passphrase)?;
+            if sign_args.detached {
+                sign_file_detached(
+                    sign_args.file_path,
+                    sign_args.key_path,
+                    sign_args.output_file_path,
+                    passphrase.as_deref(),
+                )?;
+            } else {
+                sign_file(
+                    sign_args.file_path,
+                    sign_args.key_path,
+                    sign_args.output_file_path,
+                    sign_args.compression_level,
+                    passphrase.as_deref(),
+                    sign_args.comment,
+                    sign_args.untrusted_comment,
+                )?;
+            }
         }
         cli::Commands::Verify(verify_args) => {
-            verify_file(
-                verify_args.file_path,
-                verify_args.key_path,
-                verify_args.output_file_path,
+            if let Some(signature_path) = verify_args.detached {
+                verify_file_detached(verify_args.file_path, verify_args.key_path, signature_path)?;
+            } else {
+                verify_file(
+                    verify_args.file_path,
+                    verify_args.key_path,
+                    verify_args.output_file_path,
+                )?;
+            }
+        }
+        cli::Commands::VerifyThreshold(vt_args) => {
+            let trusted_keys = vt_args
+                .trusted_key_paths
+                .iter()
+                .map(read_verifying_key_from_file)
+                .collect::<Result<Vec<_>, _>>()?;
+            verify_file_threshold(
+                vt_args.file_path,
+                vt_args.output_file_path,
+                &trusted_keys,
+                vt_args.threshold,
+            )?;
+        }
+        cli::Commands::Append(append_args) => {
+            let passphrase = prompt_passphrase_if_needed(append_args.passphrase)?;
+            append_signature(
+                append_args.file_path,
+                append_args.bundle_path,
+                append_args.key_path,
+                passphrase.as_deref(),
             )?;
         }
         cli::Commands::Generate(gen_args) => {
-            generate_keypair(gen_args.private_key_path, gen_args.public_key_path)?;
+            let passphrase = prompt_passphrase_if_needed(gen_args.passphrase)?;
+            generate_keypair(
+                gen_args.private_key_path,
+                gen_args.public_key_path,
+                passphrase.as_deref(),
+            )?;
+        }
+        cli::Commands::Fetch(fetch_args) => {
+            fetch_and_verify_file(
+                &fetch_args.url,
+                fetch_args.key_path,
+                fetch_args.bundle_path,
+                fetch_args.output_file_path,
+                |completed_bytes, total_bytes| match total_bytes {
+                    Some(total_bytes) => info!("Downloaded {completed_bytes}/{total_bytes} bytes"),
+                    None => info!("Downloaded {completed_bytes} bytes"),
+                },
+            )?;
         }
         cli::Commands::BuildComplete => build_complete_file(),
     }
     Ok(())
 }
 
+/// Prompt the user for a passphrase on the terminal when `required` is set, returning `None` otherwise.
+fn prompt_passphrase_if_needed(required: bool) -> Result<Option<String>, BinsignError> {
+    if !required {
+        return Ok(None);
+    }
+    let passphrase = prompt_password("Passphrase: ").map_err(BinsignError::PassphrasePrompt)?;
+    Ok(Some(passphrase))
+}
+
 fn build_complete_file() {
     const BIN_NAME: &str = env!("CARGO_BIN_NAME");
     let base_dir = Path::new("complete");
@@ -6,37 +6,167 @@
  * license that can be found in the LICENSE file
  */
 
-use ed25519_dalek::Signature;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{Signature, SIGNATURE_LENGTH};
 use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
 
 use crate::BinsignError;
 
-/// Representation of a signed file.
+/// Size, in bytes, of a key id: the first 8 bytes of the blake3 hash of the verifying key's DER encoding.
+pub const KEY_ID_LENGTH: usize = 8;
+
+/// A short fingerprint identifying which key produced a given signature.
+pub type KeyId = [u8; KEY_ID_LENGTH];
+
+/// Size, in bytes, of a [`DetachedSignature`] once decoded from base64: the key id followed by the signature.
+const DETACHED_SIGNATURE_LENGTH: usize = KEY_ID_LENGTH + SIGNATURE_LENGTH;
+
+/// Header prepended to a signed bundle.\
+/// The file content itself is streamed separately, right after this header, so the header only carries the
+/// signatures over the file's prehash, produced by one or more signers, the original (uncompressed) file size,
+/// and the bundle's comments.\
+/// `trusted_comment`, when set, is covered by every signature: it is concatenated with the file's blake3 prehash
+/// before signing, so it cannot be altered without invalidating the signatures. `untrusted_comment` is free-form
+/// and carried alongside the bundle without being authenticated at all.
 #[derive(Serialize, Deserialize)]
 pub struct SignedFile {
-    pub signature: Signature,
+    pub version: u8,
+    pub signatures: Vec<(KeyId, Signature)>,
     pub file_size: u64,
-    #[serde(with = "serde_bytes")]
-    pub file: Vec<u8>,
+    pub trusted_comment: Option<String>,
+    pub untrusted_comment: Option<String>,
 }
 
 impl SignedFile {
-    /// Create a new signed file with the provided binary content, the provided signature and the provided file size
-    pub fn new(file: Vec<u8>, signature: Signature, file_size: u64) -> Self {
+    /// Current bundle format version, written by every newly-signed bundle.
+    pub const CURRENT_VERSION: u8 = 1;
+
+    /// Create a new header with a single signature, the original file size and the bundle's comments.
+    pub fn new(
+        key_id: KeyId,
+        signature: Signature,
+        file_size: u64,
+        trusted_comment: Option<String>,
+        untrusted_comment: Option<String>,
+    ) -> Self {
         SignedFile {
-            signature,
+            version: Self::CURRENT_VERSION,
+            signatures: vec![(key_id, signature)],
             file_size,
-            file,
+            trusted_comment,
+            untrusted_comment,
         }
     }
-    /// Encode the signed bundle using bincode.\
-    /// Take ownership of `Self` and return the encoded file.
-    pub fn encode(self) -> Result<Vec<u8>, BinsignError> {
-        bincode::serialize(&self).map_err(BinsignError::FileEncoding)
+    /// Append another signer's signature to the header, turning it into a multi-signature bundle.
+    pub fn append_signature(&mut self, key_id: KeyId, signature: Signature) {
+        self.signatures.push((key_id, signature));
     }
-    /// Decode the signed bundle using bincode.\
-    /// Expect a `Vec<u8>` with the data to be decoded.
-    pub fn decode(data: Vec<u8>) -> Result<Self, BinsignError> {
-        bincode::deserialize(&data).map_err(BinsignError::FileDecoding)
+    /// Encode the header using bincode.
+    pub fn encode(&self) -> Result<Vec<u8>, BinsignError> {
+        bincode::serialize(self).map_err(BinsignError::FileEncoding)
+    }
+    /// Decode a header from a seekable reader, returning it alongside the bundle's [`DecodedBody`].\
+    /// For a current bundle, the body is [`DecodedBody::Streamed`]: the reader is left positioned right after the
+    /// header so the caller can stream the compressed body that follows it directly.\
+    /// Transparently upgrades a legacy single-signature bundle, written before binsign streamed files at all, into
+    /// a one-entry header whose key id is left unset and whose comments are both unset. Such bundles bincode-encode
+    /// the signature, file size and the whole zstd-compressed file as one embedded byte blob rather than streaming
+    /// the compressed body after the header, so it is returned as [`DecodedBody::Embedded`] instead of being left
+    /// for the caller to read off the reader.
+    pub fn decode<R: Read + Seek>(reader: &mut R) -> Result<(Self, DecodedBody), BinsignError> {
+        let start = reader.stream_position().map_err(BinsignError::FileIO)?;
+        if let Ok(signed_file) = bincode::deserialize_from::<_, SignedFile>(&mut *reader) {
+            return Ok((signed_file, DecodedBody::Streamed));
+        }
+        reader
+            .seek(SeekFrom::Start(start))
+            .map_err(BinsignError::FileIO)?;
+        let legacy: LegacyEmbeddedHeader =
+            bincode::deserialize_from(reader).map_err(BinsignError::FileDecoding)?;
+        let signed_file = SignedFile {
+            version: 0,
+            signatures: vec![([0u8; KEY_ID_LENGTH], legacy.signature)],
+            file_size: legacy.file_size,
+            trusted_comment: None,
+            untrusted_comment: None,
+        };
+        Ok((signed_file, DecodedBody::Embedded(legacy.file)))
+    }
+    /// The size, in bytes, of a header encoding the given number of signatures and comments.\
+    /// Since every field but the signature count and the comments' lengths is fixed-size, this lets callers
+    /// reserve the header's space in the output file before the final signature (and the header that carries it)
+    /// is known, as long as the comments that will be written are already known.
+    pub fn placeholder_size(
+        signature_count: usize,
+        trusted_comment: Option<String>,
+        untrusted_comment: Option<String>,
+    ) -> Result<u64, BinsignError> {
+        let placeholder = SignedFile {
+            version: Self::CURRENT_VERSION,
+            signatures: vec![([0u8; KEY_ID_LENGTH], Signature::from_bytes(&[0u8; 64])); signature_count],
+            file_size: 0,
+            trusted_comment,
+            untrusted_comment,
+        };
+        bincode::serialized_size(&placeholder).map_err(BinsignError::FileEncoding)
+    }
+}
+
+/// The compressed body of a decoded bundle, as returned by [`SignedFile::decode`].
+pub enum DecodedBody {
+    /// The current bundle layout: the compressed body is streamed right after the header, so the caller reads it
+    /// directly off the same reader the header came from.
+    Streamed,
+    /// The legacy, pre-streaming bundle layout: the whole zstd-compressed file was embedded in the header itself,
+    /// so it is handed back here instead of being left on the reader.
+    Embedded(Vec<u8>),
+}
+
+/// Shape of the very first bundle layout binsign ever produced: a single signature, the original file size and the
+/// whole zstd-compressed file, embedded as one bincode-encoded blob with no streaming and no version byte. Kept
+/// only so [`SignedFile::decode`] can still read bundles produced by that version.
+#[derive(Deserialize)]
+struct LegacyEmbeddedHeader {
+    signature: Signature,
+    file_size: u64,
+    #[serde(with = "serde_bytes")]
+    file: Vec<u8>,
+}
+
+/// A signature detached from its file, for workflows where the original file must be left untouched.\
+/// Carries the signer's key id alongside the signature so a verifier can tell which key produced it.\
+/// Unlike [`SignedFile`], this is not bincode-encoded: it is meant to be copy-pasted or stored in a text
+/// file, so it is represented as URL-safe base64.
+pub struct DetachedSignature {
+    pub key_id: KeyId,
+    pub signature: Signature,
+}
+
+impl DetachedSignature {
+    /// Create a new detached signature with the provided key id and signature.
+    pub fn new(key_id: KeyId, signature: Signature) -> Self {
+        DetachedSignature { key_id, signature }
+    }
+    /// Encode the key id and signature as URL-safe, unpadded base64 text.
+    pub fn to_base64(&self) -> String {
+        let mut bytes = Vec::with_capacity(DETACHED_SIGNATURE_LENGTH);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.signature.to_bytes());
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+    /// Decode a key id and signature from URL-safe, unpadded base64 text.
+    pub fn from_base64(encoded: &str) -> Result<Self, BinsignError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded.trim())
+            .map_err(BinsignError::SignatureBase64Decoding)?;
+        if bytes.len() != DETACHED_SIGNATURE_LENGTH {
+            return Err(BinsignError::MalformedDetachedSignature);
+        }
+        let mut key_id = [0u8; KEY_ID_LENGTH];
+        key_id.copy_from_slice(&bytes[..KEY_ID_LENGTH]);
+        let signature = Signature::from_slice(&bytes[KEY_ID_LENGTH..])
+            .map_err(|_| BinsignError::MalformedDetachedSignature)?;
+        Ok(DetachedSignature::new(key_id, signature))
     }
 }